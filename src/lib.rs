@@ -1,51 +1,157 @@
 use std::{
     collections::VecDeque,
     error::Error,
+    io::Write,
 };
 
+/// Sentinel used in place of `Option<u32>` for "no node" so the hot
+/// transition/fail-link tables stay plain `u32` arrays.
+const NONE: u32 = u32::MAX;
+const ROOT: u32 = 0;
+
+/// Fingerprint of the on-disk node layout. Bump this whenever the
+/// serialized shape changes so a stale or corrupt blob is rejected
+/// instead of being loaded into a mismatched automaton.
+const FORMAT_FINGERPRINT: u64 = 0x4C41_424D_4B52_0002;
+
 struct Node {
-    children: [Option<Box<Node>>; 256],
-    fail_link: Option<*mut Node>,
+    children: Vec<u32>,
+    fail_link: u32,
+    output_link: u32,
     label: Option<String>,
+    len: usize,
+    priority: i32,
+    order: u32,
 }
 
 impl Node {
-    fn new() -> Self {
+    fn new(num_classes: usize) -> Self {
         Node {
-            children: std::array::from_fn(|_| None),
-            fail_link: None,
+            children: vec![NONE; num_classes],
+            fail_link: NONE,
+            output_link: NONE,
             label: None,
+            len: 0,
+            priority: 0,
+            order: 0,
         }
     }
 }
 
+/// A single pattern hit produced by [`LabelMaker::find_all`].
+///
+/// `start` and `end` are inclusive byte offsets into the scanned text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub start: usize,
+    pub end: usize,
+    pub label: String,
+}
+
+/// Controls how [`LabelMaker`] picks a winner when multiple labeled
+/// nodes fire at the same scan position, via [`LabelMaker::set_resolution`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchResolution {
+    /// Rank firing nodes by `(priority, pattern_length)`, highest
+    /// first; ties broken by insertion order (earlier wins). The
+    /// default; when every pattern shares the same priority it ranks
+    /// exactly as `LongestOnly` does.
+    #[default]
+    PriorityThenLength,
+    /// Ignore priority and rank purely by `pattern_length`, matching
+    /// the struct's original longest-match behavior.
+    LongestOnly,
+}
+
+/// An Aho-Corasick trie addressed by `u32` indices into a flat arena
+/// rather than boxed nodes linked with raw pointers. This keeps the
+/// structure safe to move and trivially `Send`/`Sync`, since fail and
+/// output links are offsets into `nodes` instead of pointers that would
+/// dangle if `LabelMaker` were relocated.
+///
+/// Transitions are indexed through `byte_class` rather than the raw
+/// input byte: most dictionaries only ever branch on a handful of
+/// distinct bytes, so bytes that always transition identically from
+/// every state are folded into one equivalence class, keeping each
+/// node's `children` vector as small as the alphabet actually in use.
 pub struct LabelMaker {
-    root: Box<Node>,
+    nodes: Vec<Node>,
+    byte_class: [u8; 256],
+    num_classes: usize,
+    resolution: MatchResolution,
+    next_order: u32,
     _failure_links_built: bool,
 }
 
+/// Carries the state threaded through [`LabelMaker::suggest_dfs`]'s
+/// recursive walk: the fixed query/budget/byte-lookup for the search,
+/// plus the mutable path-so-far and best candidate seen.
+struct SuggestWalk<'a> {
+    query: &'a [u8],
+    max_distance: usize,
+    class_byte: &'a [u8],
+    path: Vec<u8>,
+    best: Option<((usize, usize, usize), String)>,
+}
+
 impl LabelMaker {
     fn new() -> Self {
+        let mut byte_class = [0u8; 256];
+        for (byte, class) in byte_class.iter_mut().enumerate() {
+            *class = byte as u8;
+        }
+
         Self {
-            root: Box::new(Node::new()),
+            nodes: vec![Node::new(256)],
+            byte_class,
+            num_classes: 256,
+            resolution: MatchResolution::default(),
+            next_order: 0,
             _failure_links_built: false,
         }
     }
 
+    /// Chooses how ties are broken when several labeled nodes fire at
+    /// the same scan position. See [`MatchResolution`].
+    pub fn set_resolution(&mut self, resolution: MatchResolution) {
+        self.resolution = resolution;
+    }
+
+    /// Inserts `pattern` under `label` with the default priority of `0`.
+    /// Equivalent to `insert_weighted(pattern, label, 0)`.
     pub fn insert(&mut self, pattern: &str, label: &str) -> Result<(), Box<dyn Error>> {
+        self.insert_weighted(pattern, label, 0)
+    }
+
+    /// Inserts `pattern` under `label`, recording `priority` so that
+    /// [`LabelMaker::categorize`] and [`LabelMaker::find_all`] can
+    /// prefer a short, high-confidence pattern over a longer but
+    /// lower-priority one firing at the same position. See
+    /// [`MatchResolution`] for the resulting ranking.
+    pub fn insert_weighted(
+        &mut self,
+        pattern: &str,
+        label: &str,
+        priority: i32,
+    ) -> Result<(), Box<dyn Error>> {
         if self._failure_links_built {
             return Err("Cannot insert after finalizing".into());
         }
 
-        let mut node = &mut *self.root;
+        let mut node_idx = ROOT;
         for &byte in pattern.as_bytes() {
-            let index = byte as usize;
-            if node.children[index].is_none() {
-                node.children[index] = Some(Box::new(Node::new()));
+            let index = self.byte_class[byte as usize] as usize;
+            let mut child_idx = self.nodes[node_idx as usize].children[index];
+            if child_idx == NONE {
+                self.nodes.push(Node::new(self.num_classes));
+                child_idx = (self.nodes.len() - 1) as u32;
+                self.nodes[node_idx as usize].children[index] = child_idx;
             }
-            node = node.children[index].as_mut().unwrap();
+            node_idx = child_idx;
         }
 
+        let order = self.next_order;
+        let node = &mut self.nodes[node_idx as usize];
         if let Some(existing_label) = &node.label {
             if existing_label != label {
                 return Err(format!(
@@ -56,79 +162,542 @@ impl LabelMaker {
             }
         }
         node.label = Some(label.to_string());
+        node.len = pattern.len();
+        node.priority = priority;
+        node.order = order;
+        self.next_order += 1;
 
         Ok(())
     }
 
+    /// Ranks a labeled node under the active [`MatchResolution`]: larger
+    /// is more preferred. Only meaningful for nodes that carry a label.
+    fn rank_key(&self, node_idx: u32) -> (i64, i64, i64) {
+        let node = &self.nodes[node_idx as usize];
+        let priority = match self.resolution {
+            MatchResolution::PriorityThenLength => node.priority as i64,
+            MatchResolution::LongestOnly => 0,
+        };
+        (priority, node.len as i64, -(node.order as i64))
+    }
+
+    /// Convenience wrapper over [`LabelMaker::scanner`] for callers that
+    /// already have the whole input in memory: feeds `text` through a
+    /// fresh `Scanner` in one push and returns the label of the best
+    /// match found, ranked per the active [`MatchResolution`] (by
+    /// default priority first, then pattern length), or `None` if
+    /// nothing matched.
     pub fn categorize(&self, text: &str) -> Option<String> {
+        let mut scanner = self.scanner();
+        scanner.push(text.as_bytes());
+        scanner.best_match().map(|m| m.label)
+    }
+
+    /// Convenience wrapper over [`LabelMaker::scanner`] that reports
+    /// every pattern occurrence in `text`, including ones that end at a
+    /// proper suffix of another match's path (e.g. both "she" and "he"
+    /// inside "ushers"). `start`/`end` are inclusive byte offsets.
+    pub fn find_all(&self, text: &str) -> Vec<Match> {
+        self.scanner().push(text.as_bytes())
+    }
+
+    /// Returns a [`Scanner`] positioned at the root, ready to feed the
+    /// automaton chunks of input that arrive over time (a log tailer, a
+    /// network stream) without restarting the fail-link walk or
+    /// buffering the whole input.
+    pub fn scanner(&self) -> Scanner<'_> {
         if !self._failure_links_built {
             panic!("Failure links not built yet. Call finalize() first.");
         }
 
-        let mut node = &*self.root;
-        let mut longest_match_label: Option<String> = None;
+        Scanner {
+            maker: self,
+            node_idx: ROOT,
+            offset: 0,
+            best: None,
+        }
+    }
+
+    /// Finds the label of the stored pattern closest to `text` within
+    /// `max_distance` edits, or `None` if no pattern is within budget.
+    /// Walks the trie depth-first carrying one row of a Levenshtein DP
+    /// table per node (the root holds `[0, 1, 2, ..., text.len()]`), so
+    /// patterns are scored without needing any structure beyond the
+    /// existing trie. A branch is pruned as soon as its whole
+    /// row exceeds `max_distance`. Candidates are ranked first by edit
+    /// distance, then by how closely the pattern's length and prefix
+    /// match `text`. Degrades to exact matching at `max_distance = 0`.
+    pub fn suggest(&self, text: &str, max_distance: usize) -> Option<String> {
+        if !self._failure_links_built {
+            panic!("Failure links not built yet. Call finalize() first.");
+        }
 
-        for &byte in text.as_bytes() {
-            let index = byte as usize;
+        let query = text.as_bytes();
+        let root_row: Vec<usize> = (0..=query.len()).collect();
 
-            while node.children[index].is_none() && node as *const _ != &*self.root {
-                node = unsafe { &*node.fail_link.unwrap() };
+        let mut class_byte = vec![0u8; self.num_classes];
+        let mut seen = vec![false; self.num_classes];
+        for byte in 0..256usize {
+            let class = self.byte_class[byte] as usize;
+            if !seen[class] {
+                seen[class] = true;
+                class_byte[class] = byte as u8;
             }
+        }
 
-            if let Some(ref child) = node.children[index] {
-                node = child;
-            }
+        let mut walk = SuggestWalk {
+            query,
+            max_distance,
+            class_byte: &class_byte,
+            path: Vec::new(),
+            best: None,
+        };
+        self.suggest_dfs(ROOT, &root_row, &mut walk);
 
-            if let Some(ref label) = node.label {
-                longest_match_label = Some(label.clone());
+        walk.best.map(|(_, label)| label)
+    }
+
+    fn suggest_dfs(&self, node_idx: u32, prev_row: &[usize], walk: &mut SuggestWalk) {
+        let node = &self.nodes[node_idx as usize];
+
+        if let Some(ref label) = node.label {
+            let distance = prev_row[walk.query.len()];
+            if distance <= walk.max_distance {
+                let length_diff = node.len.abs_diff(walk.query.len());
+                let shared_prefix = walk
+                    .path
+                    .iter()
+                    .zip(walk.query.iter())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                let key = (distance, length_diff, walk.query.len() - shared_prefix);
+                let is_better = match &walk.best {
+                    None => true,
+                    Some((current_key, _)) => key < *current_key,
+                };
+                if is_better {
+                    walk.best = Some((key, label.clone()));
+                }
             }
         }
 
-        longest_match_label
-    }
+        for class_id in 0..self.num_classes {
+            let child_idx = node.children[class_id];
+            if child_idx == NONE {
+                continue;
+            }
+            let byte = walk.class_byte[class_id];
+
+            let mut next_row = vec![0usize; walk.query.len() + 1];
+            next_row[0] = prev_row[0] + 1;
+            for j in 1..=walk.query.len() {
+                let cost = if byte == walk.query[j - 1] { 0 } else { 1 };
+                let deletion = prev_row[j] + 1;
+                let insertion = next_row[j - 1] + 1;
+                let substitution = prev_row[j - 1] + cost;
+                next_row[j] = deletion.min(insertion).min(substitution);
+            }
 
+            if *next_row.iter().min().unwrap() <= walk.max_distance {
+                walk.path.push(byte);
+                self.suggest_dfs(child_idx, &next_row, walk);
+                walk.path.pop();
+            }
+        }
+    }
 
     pub fn finalize(&mut self) {
-        let root_ptr: *mut Node = &mut *self.root;
+        self.compress_byte_classes();
+
         let mut queue = VecDeque::new();
 
-        for i in 0..256 {
-            if let Some(ref mut child) = self.root.children[i] {
-                child.fail_link = Some(root_ptr);
-                queue.push_back(child.as_mut() as *mut Node);
+        for i in 0..self.num_classes {
+            let child_idx = self.nodes[ROOT as usize].children[i];
+            if child_idx != NONE {
+                self.nodes[child_idx as usize].fail_link = ROOT;
+                queue.push_back(child_idx);
             }
         }
 
         while let Some(current) = queue.pop_front() {
-            for i in 0..256 {
-                unsafe {
-                    if let Some(ref mut child) = (*current).children[i] {
-                        let mut fail = (*current).fail_link.unwrap();
-
-                        while fail != root_ptr && (*fail).children[i].is_none() {
-                            if let Some(new_fail) = (*fail).fail_link {
-                                fail = new_fail;
-                            } else {
-                                fail = root_ptr;
-                                break;
-                            }
-                        }
-
-                        if let Some(ref mut sibling) = (*fail).children[i] {
-                            child.fail_link = Some(sibling.as_mut() as *mut Node);
-                        } else {
-                            child.fail_link = Some(root_ptr);
-                        }
-
-
-                        queue.push_back(child.as_mut() as *mut Node);
-                    }
+            for i in 0..self.num_classes {
+                let child_idx = self.nodes[current as usize].children[i];
+                if child_idx == NONE {
+                    continue;
+                }
+
+                let mut fail = self.nodes[current as usize].fail_link;
+                while fail != ROOT && self.nodes[fail as usize].children[i] == NONE {
+                    fail = self.nodes[fail as usize].fail_link;
                 }
+
+                let resolved = self.nodes[fail as usize].children[i];
+                let fail_target = if resolved != NONE { resolved } else { ROOT };
+                self.nodes[child_idx as usize].fail_link = fail_target;
+
+                self.nodes[child_idx as usize].output_link = if self.nodes[fail_target as usize].label.is_some() {
+                    fail_target
+                } else {
+                    self.nodes[fail_target as usize].output_link
+                };
+
+                queue.push_back(child_idx);
             }
         }
 
         self._failure_links_built = true;
     }
+
+    /// Groups raw bytes into equivalence classes: two bytes are
+    /// equivalent if every node in the trie transitions on them
+    /// identically. Remaps `byte_class` and compacts every node's
+    /// `children` down to one slot per class instead of one per byte.
+    /// Runs once, before fail links are built, while `byte_class` is
+    /// still the identity mapping inserts were built against.
+    fn compress_byte_classes(&mut self) {
+        let mut class_of: [i32; 256] = [-1; 256];
+        let mut representatives: Vec<u8> = Vec::new();
+
+        for byte in 0..256usize {
+            if class_of[byte] != -1 {
+                continue;
+            }
+
+            let class_id = representatives.len() as i32;
+            class_of[byte] = class_id;
+            representatives.push(byte as u8);
+
+            for (other, slot) in class_of.iter_mut().enumerate().skip(byte + 1) {
+                if *slot != -1 {
+                    continue;
+                }
+                if self.nodes.iter().all(|node| node.children[byte] == node.children[other]) {
+                    *slot = class_id;
+                }
+            }
+        }
+
+        let num_classes = representatives.len();
+        let mut byte_class = [0u8; 256];
+        for byte in 0..256 {
+            byte_class[byte] = class_of[byte] as u8;
+        }
+
+        for node in &mut self.nodes {
+            let mut compacted = vec![NONE; num_classes];
+            for (class_id, &rep_byte) in representatives.iter().enumerate() {
+                compacted[class_id] = node.children[rep_byte as usize];
+            }
+            node.children = compacted;
+        }
+
+        self.byte_class = byte_class;
+        self.num_classes = num_classes;
+    }
+
+    /// Writes the node table, fail/output links, labels and a format
+    /// fingerprint to `out` so a finalized `LabelMaker` can be reloaded
+    /// with [`LabelMaker::deserialize`] without repeating `insert`/
+    /// `finalize`.
+    pub fn serialize(&self, mut out: impl Write) -> std::io::Result<()> {
+        out.write_all(&FORMAT_FINGERPRINT.to_le_bytes())?;
+        out.write_all(&(self.num_classes as u32).to_le_bytes())?;
+        out.write_all(&self.byte_class)?;
+        out.write_all(&[self.resolution as u8])?;
+        out.write_all(&self.next_order.to_le_bytes())?;
+        out.write_all(&(self.nodes.len() as u32).to_le_bytes())?;
+
+        for node in &self.nodes {
+            for &child in &node.children {
+                out.write_all(&child.to_le_bytes())?;
+            }
+            out.write_all(&node.fail_link.to_le_bytes())?;
+            out.write_all(&node.output_link.to_le_bytes())?;
+            out.write_all(&(node.len as u32).to_le_bytes())?;
+            out.write_all(&node.priority.to_le_bytes())?;
+            out.write_all(&node.order.to_le_bytes())?;
+
+            match &node.label {
+                Some(label) => {
+                    out.write_all(&[1])?;
+                    out.write_all(&(label.len() as u32).to_le_bytes())?;
+                    out.write_all(label.as_bytes())?;
+                }
+                None => out.write_all(&[0])?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rebuilds a finalized `LabelMaker` from a buffer produced by
+    /// [`LabelMaker::serialize`]. Errors cleanly on a format mismatch, a
+    /// truncated buffer, or a same-length buffer whose node table is
+    /// internally inconsistent (out-of-bounds indices, or a labeled
+    /// node's length disagreeing with its trie depth) instead of
+    /// producing a corrupt automaton that panics on first use. The
+    /// result is immediately usable by `categorize`/`find_all`.
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let mut cursor = 0usize;
+
+        let fingerprint = read_u64(bytes, &mut cursor)?;
+        if fingerprint != FORMAT_FINGERPRINT {
+            return Err(format!(
+                "Unrecognized LabelMaker format fingerprint {:#x}, expected {:#x}",
+                fingerprint, FORMAT_FINGERPRINT
+            )
+            .into());
+        }
+
+        let num_classes = read_u32(bytes, &mut cursor)? as usize;
+        let byte_class = read_byte_class(bytes, &mut cursor)?;
+        let resolution = match read_u8(bytes, &mut cursor)? {
+            0 => MatchResolution::PriorityThenLength,
+            1 => MatchResolution::LongestOnly,
+            other => return Err(format!("Unrecognized MatchResolution tag {}", other).into()),
+        };
+        let next_order = read_u32(bytes, &mut cursor)?;
+        let node_count = read_u32(bytes, &mut cursor)? as usize;
+
+        let mut nodes = Vec::with_capacity(node_count);
+        for _ in 0..node_count {
+            let mut children = Vec::with_capacity(num_classes);
+            for _ in 0..num_classes {
+                children.push(read_u32(bytes, &mut cursor)?);
+            }
+            let fail_link = read_u32(bytes, &mut cursor)?;
+            let output_link = read_u32(bytes, &mut cursor)?;
+            let len = read_u32(bytes, &mut cursor)? as usize;
+            let priority = read_u32(bytes, &mut cursor)? as i32;
+            let order = read_u32(bytes, &mut cursor)?;
+
+            let label = if read_u8(bytes, &mut cursor)? == 1 {
+                let label_len = read_u32(bytes, &mut cursor)? as usize;
+                let label_bytes = read_bytes(bytes, &mut cursor, label_len)?;
+                Some(String::from_utf8(label_bytes.to_vec())?)
+            } else {
+                None
+            };
+
+            nodes.push(Node {
+                children,
+                fail_link,
+                output_link,
+                label,
+                len,
+                priority,
+                order,
+            });
+        }
+
+        validate_node_graph(&nodes, num_classes)?;
+
+        Ok(Self {
+            nodes,
+            byte_class,
+            num_classes,
+            resolution,
+            next_order,
+            _failure_links_built: true,
+        })
+    }
+}
+
+/// A handle into a finalized [`LabelMaker`] that carries the current
+/// trie node and an absolute byte offset across calls to `push`, so a
+/// caller can feed arbitrary chunks of a log or stream without
+/// restarting the fail-link walk at each call or buffering the whole
+/// input.
+pub struct Scanner<'a> {
+    maker: &'a LabelMaker,
+    node_idx: u32,
+    offset: usize,
+    best: Option<(u32, usize)>,
+}
+
+impl<'a> Scanner<'a> {
+    /// Feeds `chunk` into the automaton, continuing from wherever the
+    /// previous `push` left off, and returns every match completed
+    /// inside this chunk in scan order; matches that end at the same
+    /// byte are ranked best-first per the active [`MatchResolution`].
+    /// `start`/`end` on the returned `Match`es are
+    /// absolute offsets into the whole stream, not just this chunk.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<Match> {
+        let mut matches = Vec::new();
+        let mut firing = Vec::new();
+
+        for &byte in chunk {
+            let index = self.maker.byte_class[byte as usize] as usize;
+
+            while self.maker.nodes[self.node_idx as usize].children[index] == NONE
+                && self.node_idx != ROOT
+            {
+                self.node_idx = self.maker.nodes[self.node_idx as usize].fail_link;
+            }
+
+            let child_idx = self.maker.nodes[self.node_idx as usize].children[index];
+            if child_idx != NONE {
+                self.node_idx = child_idx;
+            }
+
+            let end = self.offset;
+            let node = &self.maker.nodes[self.node_idx as usize];
+
+            firing.clear();
+            if node.label.is_some() {
+                firing.push(self.node_idx);
+            }
+            let mut output_idx = node.output_link;
+            while output_idx != NONE {
+                let out_node = &self.maker.nodes[output_idx as usize];
+                if out_node.label.is_some() {
+                    firing.push(output_idx);
+                }
+                output_idx = out_node.output_link;
+            }
+            firing.sort_by_key(|&idx| std::cmp::Reverse(self.maker.rank_key(idx)));
+
+            if let Some(&top) = firing.first() {
+                let challenger = self.maker.rank_key(top);
+                let is_better = match self.best {
+                    None => true,
+                    Some((best_idx, _)) => challenger > self.maker.rank_key(best_idx),
+                };
+                if is_better {
+                    self.best = Some((top, end));
+                }
+            }
+
+            for &idx in &firing {
+                let fired = &self.maker.nodes[idx as usize];
+                matches.push(Match {
+                    start: end + 1 - fired.len,
+                    end,
+                    label: fired.label.clone().unwrap(),
+                });
+            }
+
+            self.offset += 1;
+        }
+
+        matches
+    }
+
+    /// Returns the best match seen across every `push` call so far,
+    /// ranked per the active [`MatchResolution`].
+    pub fn best_match(&self) -> Option<Match> {
+        self.best.map(|(node_idx, end)| {
+            let node = &self.maker.nodes[node_idx as usize];
+            Match {
+                start: end + 1 - node.len,
+                end,
+                label: node.label.clone().unwrap(),
+            }
+        })
+    }
+}
+
+fn read_bytes<'a>(
+    bytes: &'a [u8],
+    cursor: &mut usize,
+    len: usize,
+) -> Result<&'a [u8], Box<dyn Error>> {
+    let end = cursor
+        .checked_add(len)
+        .ok_or("LabelMaker buffer length overflow")?;
+    if end > bytes.len() {
+        return Err("Truncated LabelMaker buffer".into());
+    }
+    let slice = &bytes[*cursor..end];
+    *cursor = end;
+    Ok(slice)
+}
+
+fn read_u8(bytes: &[u8], cursor: &mut usize) -> Result<u8, Box<dyn Error>> {
+    Ok(read_bytes(bytes, cursor, 1)?[0])
+}
+
+fn read_u32(bytes: &[u8], cursor: &mut usize) -> Result<u32, Box<dyn Error>> {
+    let slice = read_bytes(bytes, cursor, 4)?;
+    Ok(u32::from_le_bytes(slice.try_into().unwrap()))
+}
+
+fn read_u64(bytes: &[u8], cursor: &mut usize) -> Result<u64, Box<dyn Error>> {
+    let slice = read_bytes(bytes, cursor, 8)?;
+    Ok(u64::from_le_bytes(slice.try_into().unwrap()))
+}
+
+fn read_byte_class(bytes: &[u8], cursor: &mut usize) -> Result<[u8; 256], Box<dyn Error>> {
+    let slice = read_bytes(bytes, cursor, 256)?;
+    let mut byte_class = [0u8; 256];
+    byte_class.copy_from_slice(slice);
+    Ok(byte_class)
+}
+
+/// Rejects a deserialized node table that is structurally corrupt
+/// rather than merely truncated: every `children`/`fail_link`/
+/// `output_link` index must point at a real node (or be `NONE`), and
+/// every labeled node's recorded `len` must match its actual depth in
+/// the trie (patterns are inserted one node per byte, so depth and
+/// length always agree in a genuine `LabelMaker`). Without this, a
+/// same-length blob with a flipped index or length field would build
+/// successfully and only panic later, the first time it was scanned.
+fn validate_node_graph(nodes: &[Node], num_classes: usize) -> Result<(), Box<dyn Error>> {
+    let node_count = nodes.len();
+
+    for node in nodes {
+        if node.children.len() != num_classes {
+            return Err("Corrupt LabelMaker buffer: node has wrong number of children".into());
+        }
+        for &child in &node.children {
+            if child != NONE && child as usize >= node_count {
+                return Err("Corrupt LabelMaker buffer: child index out of bounds".into());
+            }
+        }
+        if node.fail_link != NONE && node.fail_link as usize >= node_count {
+            return Err("Corrupt LabelMaker buffer: fail_link index out of bounds".into());
+        }
+        if node.output_link != NONE && node.output_link as usize >= node_count {
+            return Err("Corrupt LabelMaker buffer: output_link index out of bounds".into());
+        }
+    }
+
+    if node_count == 0 {
+        return Ok(());
+    }
+
+    let mut depth: Vec<Option<usize>> = vec![None; node_count];
+    depth[ROOT as usize] = Some(0);
+    let mut queue = VecDeque::new();
+    queue.push_back(ROOT);
+    while let Some(current) = queue.pop_front() {
+        let current_depth = depth[current as usize].unwrap();
+        for &child in &nodes[current as usize].children {
+            if child == NONE {
+                continue;
+            }
+            match depth[child as usize] {
+                None => {
+                    depth[child as usize] = Some(current_depth + 1);
+                    queue.push_back(child);
+                }
+                Some(existing) if existing != current_depth + 1 => {
+                    return Err("Corrupt LabelMaker buffer: inconsistent node depth".into());
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    for (idx, node) in nodes.iter().enumerate() {
+        if node.label.is_some() && depth[idx] != Some(node.len) {
+            return Err(
+                "Corrupt LabelMaker buffer: labeled node length does not match trie depth".into(),
+            );
+        }
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -191,5 +760,210 @@ mod test {
 
         assert_eq!(result, "Many");
     }
-}
 
+    #[test]
+    fn test_find_all_reports_overlapping_suffix_matches() {
+        let mut labeler = super::LabelMaker::new();
+        labeler.insert("hers", "Possessive").unwrap();
+        labeler.insert("she", "Subject").unwrap();
+        labeler.insert("he", "Subject").unwrap();
+        labeler.finalize();
+
+        let text = "ushers";
+        let mut matches = labeler.find_all(text);
+        matches.sort_by_key(|m| (m.start, m.end));
+
+        assert_eq!(
+            matches,
+            vec![
+                super::Match { start: 1, end: 3, label: "Subject".to_string() },
+                super::Match { start: 2, end: 3, label: "Subject".to_string() },
+                super::Match { start: 2, end: 5, label: "Possessive".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_all_returns_empty_vec_when_nothing_matches() {
+        let mut labeler = super::LabelMaker::new();
+        labeler.insert("Tyrannosaurus rex", "Therapod").unwrap();
+        labeler.finalize();
+
+        assert!(labeler.find_all("Stegosaurus").is_empty());
+    }
+
+    #[test]
+    fn test_serialize_deserialize_round_trip_preserves_categorize() {
+        let mut labeler = super::LabelMaker::new();
+        labeler.insert("triceratop", "Single").unwrap();
+        labeler.insert("triceratops", "Many").unwrap();
+        labeler.finalize();
+
+        let mut buffer = Vec::new();
+        labeler.serialize(&mut buffer).unwrap();
+
+        let restored = super::LabelMaker::deserialize(&buffer).unwrap();
+        let text = "triceratops are a group of herbivorous ceratopsid dinosaurs";
+
+        assert_eq!(restored.categorize(text), Some("Many".to_string()));
+    }
+
+    #[test]
+    fn test_deserialize_rejects_mismatched_fingerprint() {
+        let bytes = 0xBAD_u64.to_le_bytes();
+        let result = super::LabelMaker::deserialize(&bytes);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_deserialize_rejects_truncated_buffer() {
+        let mut labeler = super::LabelMaker::new();
+        labeler.insert("rex", "Therapod").unwrap();
+        labeler.finalize();
+
+        let mut buffer = Vec::new();
+        labeler.serialize(&mut buffer).unwrap();
+        buffer.truncate(buffer.len() - 4);
+
+        let result = super::LabelMaker::deserialize(&buffer);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_deserialize_rejects_out_of_bounds_child_index() {
+        let mut labeler = super::LabelMaker::new();
+        labeler.insert("rex", "Therapod").unwrap();
+        labeler.finalize();
+
+        let mut buffer = Vec::new();
+        labeler.serialize(&mut buffer).unwrap();
+
+        let num_classes = u32::from_le_bytes(buffer[8..12].try_into().unwrap()) as usize;
+        let children_start = 8 + 4 + 256 + 1 + 4 + 4;
+        let mut corrupted = false;
+        for class in 0..num_classes {
+            let offset = children_start + class * 4;
+            let value = u32::from_le_bytes(buffer[offset..offset + 4].try_into().unwrap());
+            if value != u32::MAX {
+                buffer[offset..offset + 4].copy_from_slice(&999_999u32.to_le_bytes());
+                corrupted = true;
+                break;
+            }
+        }
+        assert!(corrupted, "expected root to have at least one real child");
+
+        let result = super::LabelMaker::deserialize(&buffer);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_deserialize_rejects_len_inconsistent_with_trie_depth() {
+        let mut labeler = super::LabelMaker::new();
+        labeler.insert("rex", "Therapod").unwrap();
+        labeler.finalize();
+
+        let mut buffer = Vec::new();
+        labeler.serialize(&mut buffer).unwrap();
+
+        let label_pos = buffer
+            .windows(b"Therapod".len())
+            .position(|window| window == b"Therapod")
+            .expect("serialized label bytes not found");
+        let label_len_pos = label_pos - 4;
+        let tag_pos = label_len_pos - 1;
+        let order_pos = tag_pos - 4;
+        let priority_pos = order_pos - 4;
+        let len_pos = priority_pos - 4;
+        buffer[len_pos..len_pos + 4].copy_from_slice(&999_999u32.to_le_bytes());
+
+        let result = super::LabelMaker::deserialize(&buffer);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_scanner_finds_matches_split_across_chunk_boundary() {
+        let mut labeler = super::LabelMaker::new();
+        labeler.insert("Tyrannosaurus rex", "Therapod").unwrap();
+        labeler.finalize();
+
+        let mut scanner = labeler.scanner();
+        let mut matches = scanner.push(b"a Tyrannosau");
+        matches.extend(scanner.push(b"rus rex fossil"));
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].label, "Therapod");
+        assert_eq!(matches[0].start, 2);
+        assert_eq!(matches[0].end, 18);
+    }
+
+    #[test]
+    fn test_insert_weighted_priority_beats_longer_match() {
+        let mut labeler = super::LabelMaker::new();
+        labeler
+            .insert_weighted("raptor", "HighConfidence", 10)
+            .unwrap();
+        labeler
+            .insert_weighted("velociraptor", "Background", 0)
+            .unwrap();
+        labeler.finalize();
+
+        let result = labeler.categorize("a velociraptor ran");
+
+        assert_eq!(result, Some("HighConfidence".to_string()));
+    }
+
+    #[test]
+    fn test_insert_weighted_ties_fall_back_to_length_then_insertion_order() {
+        let mut labeler = super::LabelMaker::new();
+        labeler.insert_weighted("raptor", "Short", 5).unwrap();
+        labeler.insert_weighted("velociraptor", "Long", 5).unwrap();
+        labeler.finalize();
+
+        let result = labeler.categorize("a velociraptor ran");
+
+        assert_eq!(result, Some("Long".to_string()));
+
+        let mut labeler = super::LabelMaker::new();
+        labeler.insert_weighted("rex", "First", 5).unwrap();
+        labeler.insert_weighted("rap", "Second", 5).unwrap();
+        labeler.finalize();
+
+        let result = labeler.categorize("rap rex");
+
+        assert_eq!(result, Some("First".to_string()));
+    }
+
+    #[test]
+    fn test_suggest_corrects_a_single_typo_within_budget() {
+        let mut labeler = super::LabelMaker::new();
+        labeler.insert("Tyrannosaurus", "Therapod").unwrap();
+        labeler.insert("Brachiosaurus", "Saurapod").unwrap();
+        labeler.finalize();
+
+        let result = labeler.suggest("Tyranosaurus", 1);
+
+        assert_eq!(result, Some("Therapod".to_string()));
+    }
+
+    #[test]
+    fn test_suggest_returns_none_outside_distance_budget() {
+        let mut labeler = super::LabelMaker::new();
+        labeler.insert("Tyrannosaurus", "Therapod").unwrap();
+        labeler.finalize();
+
+        assert_eq!(labeler.suggest("Stegosaurus", 1), None);
+    }
+
+    #[test]
+    fn test_suggest_degrades_to_exact_match_at_zero_distance() {
+        let mut labeler = super::LabelMaker::new();
+        labeler.insert("Tyrannosaurus", "Therapod").unwrap();
+        labeler.finalize();
+
+        assert_eq!(
+            labeler.suggest("Tyrannosaurus", 0),
+            Some("Therapod".to_string())
+        );
+        assert_eq!(labeler.suggest("Tyranosaurus", 0), None);
+    }
+}